@@ -34,4 +34,13 @@ fn main() {
 
     // Also copy to the project root for debugging
     fs::copy(&linker_path, "target/memory.x").ok();
+
+    // Opt-in: set SAG2LD_MAP_REPORT to also write a memory.map next to
+    // memory.x, for eyeballing placement without building the ELF.
+    if env::var_os("SAG2LD_MAP_REPORT").is_some() {
+        let map_report = sag.to_map_report(&config);
+        let map_path = out_dir.join("memory.map");
+        fs::write(&map_path, map_report).expect("Failed to write memory.map");
+        fs::copy(&map_path, "target/memory.map").ok();
+    }
 }
@@ -16,6 +16,44 @@ pub enum SagError {
     Parse { line: usize, message: String },
     #[error("Invalid address: {0}")]
     InvalidAddress(String),
+    #[error("ELF error: {0}")]
+    Elf(String),
+}
+
+/// Errors produced by [`SagFile::validate`] describing a memory layout
+/// that `to_linker_script` would happily emit but that is broken:
+/// placement outside every configured region, overlapping regions, or a
+/// writable region mapped into read/execute-only memory.
+#[derive(Debug, thiserror::Error)]
+pub enum LayoutError {
+    #[error(
+        "region '{region}' VMA range [{start:#010X}, {end:#010X}) is not fully contained in any configured MemoryRegion"
+    )]
+    VmaOutOfBounds { region: String, start: u64, end: u64 },
+    #[error(
+        "region '{region}' LMA range [{start:#010X}, {end:#010X}) is not fully contained in any configured MemoryRegion"
+    )]
+    LmaOutOfBounds { region: String, start: u64, end: u64 },
+    #[error(
+        "regions '{first}' and '{second}' overlap in memory region '{mem_region}': [{first_start:#010X}, {first_end:#010X}) overlaps [{second_start:#010X}, {second_end:#010X})"
+    )]
+    Overlap {
+        mem_region: String,
+        first: String,
+        first_start: u64,
+        first_end: u64,
+        second: String,
+        second_start: u64,
+        second_end: u64,
+    },
+    #[error(
+        "region '{region}' places a writable section in memory region '{mem_region}' ({attributes}), which is not writable"
+    )]
+    WritableIntoReadOnly {
+        region: String,
+        mem_region: String,
+        attributes: String,
+    },
 }
 
 /// Represents an address that can be absolute or relative
@@ -67,6 +105,12 @@ pub enum Directive {
     Section { pattern: String, keep: bool },
     /// `STACK = address` - set stack pointer
     Stack(u64),
+    /// A named symbol fixed to a constant address, distinct from `STACK`.
+    /// Not produced by the SAG parser itself (the SAG grammar has no such
+    /// directive) -- only by [`LinkerScript::to_sag_file`], to preserve a
+    /// non-stack constant symbol (e.g. `_heap_size = 0x2000;`) instead of
+    /// collapsing it into a bogus stack assignment.
+    Constant { symbol: String, value: u64 },
 }
 
 /// A memory region within a block
@@ -93,10 +137,16 @@ pub struct SagFile {
     pub blocks: Vec<Block>,
 }
 
+/// Block keywords that may start a new block; also used by recovery to
+/// find a safe resumption point after a parse error.
+const BLOCK_KEYWORDS: [&str; 5] = ["HEAD", "MEM", "LDSECTION", "EXEC", "DATA"];
+
 /// Parser state machine
 struct Parser<'a> {
     lines: Vec<&'a str>,
     current: usize,
+    /// Line number pushed for each unmatched `{`, innermost last.
+    brace_stack: Vec<usize>,
 }
 
 impl<'a> Parser<'a> {
@@ -104,6 +154,7 @@ impl<'a> Parser<'a> {
         Self {
             lines: content.lines().collect(),
             current: 0,
+            brace_stack: Vec::new(),
         }
     }
 
@@ -137,9 +188,28 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse(&mut self) -> Result<SagFile, SagError> {
+    /// Build an error for EOF/unexpected-token recovery that blames the
+    /// innermost still-open `{`, since that's almost always the brace
+    /// missing its matching `}`.
+    fn unclosed_brace_error(&self) -> SagError {
+        match self.brace_stack.last() {
+            Some(&open_line) => SagError::Parse {
+                line: self.line_number(),
+                message: format!("unexpected end of file; unclosed '{{' opened at line {}", open_line),
+            },
+            None => self.parse_error("unexpected end of file, expected '}'"),
+        }
+    }
+
+    /// Parse the whole input, accumulating rather than aborting on the
+    /// first `SagError`. On an error inside a block, the brace stack is
+    /// reset and the scan fast-forwards to the next line that looks like a
+    /// fresh block (a column-0 `USER_SECTIONS` or block keyword), so one
+    /// malformed block doesn't swallow everything after it.
+    fn parse_resilient(&mut self) -> (SagFile, Vec<SagError>) {
         let mut user_sections = Vec::new();
         let mut blocks = Vec::new();
+        let mut errors = Vec::new();
 
         while self.current_line().is_some() {
             self.skip_empty_and_comments();
@@ -156,28 +226,67 @@ impl<'a> Parser<'a> {
 
             // Parse USER_SECTIONS
             if line.starts_with("USER_SECTIONS") {
-                let section = line
-                    .strip_prefix("USER_SECTIONS")
-                    .ok_or_else(|| self.parse_error("Expected section name after USER_SECTIONS"))?
-                    .trim();
+                let section = line.strip_prefix("USER_SECTIONS").unwrap_or("").trim();
                 user_sections.push(section.to_string());
                 self.advance();
                 continue;
             }
 
-            // Parse block (HEAD, MEM, LDSECTION, EXEC, DATA)
-            if let Some(block) = self.try_parse_block()? {
-                blocks.push(block);
+            // A `}` with nothing open at top level: record it and move on
+            // instead of letting try_parse_region/try_parse_block choke on it.
+            if line.starts_with('}') {
+                errors.push(self.parse_error("unmatched '}' with no open block"));
+                self.advance();
                 continue;
             }
 
+            // Parse block (HEAD, MEM, LDSECTION, EXEC, DATA)
+            match self.try_parse_block() {
+                Ok(Some(block)) => {
+                    blocks.push(block);
+                    continue;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    errors.push(err);
+                    self.brace_stack.clear();
+                    self.recover_to_next_block();
+                    continue;
+                }
+            }
+
             self.advance();
         }
 
-        Ok(SagFile {
-            user_sections,
-            blocks,
-        })
+        (
+            SagFile {
+                user_sections,
+                blocks,
+            },
+            errors,
+        )
+    }
+
+    /// After a parse error, skip forward to the next line that begins (at
+    /// column 0, after comment stripping) with a known block keyword or
+    /// `USER_SECTIONS`, so recovery doesn't key off a stray `{` inside the
+    /// broken block.
+    fn recover_to_next_block(&mut self) {
+        loop {
+            self.advance();
+            let Some(raw_line) = self.current_line() else {
+                return;
+            };
+            let code = raw_line.split(';').next().unwrap_or("");
+            let at_column_zero = code.chars().next().map(|c| !c.is_whitespace()).unwrap_or(false);
+            let trimmed = code.trim_start();
+            if at_column_zero
+                && (trimmed.starts_with("USER_SECTIONS")
+                    || BLOCK_KEYWORDS.iter().any(|bt| trimmed.starts_with(bt)))
+            {
+                return;
+            }
+        }
     }
 
     fn try_parse_block(&mut self) -> Result<Option<Block>, SagError> {
@@ -185,11 +294,10 @@ impl<'a> Parser<'a> {
         let line = line.split(';').next().unwrap_or("").trim();
 
         // Check for block keywords
-        let block_types = ["HEAD", "MEM", "LDSECTION", "EXEC", "DATA"];
         let mut block_type = None;
         let mut rest = line;
 
-        for bt in block_types {
+        for bt in BLOCK_KEYWORDS {
             if line.starts_with(bt) {
                 block_type = Some(bt.to_string());
                 rest = line[bt.len()..].trim();
@@ -228,6 +336,7 @@ impl<'a> Parser<'a> {
         if !line.trim().starts_with('{') {
             return Err(self.parse_error("Expected '{'"));
         }
+        self.brace_stack.push(self.line_number());
         self.advance();
 
         // Parse regions
@@ -235,11 +344,12 @@ impl<'a> Parser<'a> {
         loop {
             self.skip_empty_and_comments();
             let Some(line) = self.current_line() else {
-                return Err(self.parse_error("Unexpected end of file, expected '}'"));
+                return Err(self.unclosed_brace_error());
             };
 
             let line = line.split(';').next().unwrap_or("").trim();
             if line.starts_with('}') {
+                self.brace_stack.pop();
                 self.advance();
                 break;
             }
@@ -296,6 +406,7 @@ impl<'a> Parser<'a> {
         if !line.trim().starts_with('{') {
             return Err(self.parse_error("Expected '{' after region"));
         }
+        self.brace_stack.push(self.line_number());
         self.advance();
 
         // Parse directives
@@ -303,11 +414,12 @@ impl<'a> Parser<'a> {
         loop {
             self.skip_empty_and_comments();
             let Some(line) = self.current_line() else {
-                return Err(self.parse_error("Unexpected end of file in region"));
+                return Err(self.unclosed_brace_error());
             };
 
             let line = line.split(';').next().unwrap_or("").trim();
             if line.starts_with('}') {
+                self.brace_stack.pop();
                 self.advance();
                 break;
             }
@@ -383,10 +495,29 @@ impl<'a> Parser<'a> {
 }
 
 impl SagFile {
-    /// Parse a SAG file from a string
+    /// Parse a SAG file from a string, stopping at the first error.
+    ///
+    /// This is a thin wrapper around [`SagFile::parse_recovering`] that
+    /// discards the best-effort AST and surfaces only the first
+    /// accumulated diagnostic, for callers that just want `Result`.
     pub fn parse(content: &str) -> Result<Self, SagError> {
+        let (file, mut errors) = Self::parse_recovering(content);
+        if errors.is_empty() {
+            Ok(file.expect("parse_recovering always returns Some when there are no errors"))
+        } else {
+            Err(errors.remove(0))
+        }
+    }
+
+    /// Parse a SAG file, recovering from errors instead of aborting at the
+    /// first one. Returns a best-effort AST (built from every block that
+    /// parsed cleanly) alongside every `SagError` encountered, so tooling
+    /// can report all of them in one pass rather than fix-and-rerun one at
+    /// a time.
+    pub fn parse_recovering(content: &str) -> (Option<SagFile>, Vec<SagError>) {
         let mut parser = Parser::new(content);
-        parser.parse()
+        let (file, errors) = parser.parse_resilient();
+        (Some(file), errors)
     }
 
     /// Parse a SAG file from a path
@@ -403,9 +534,15 @@ impl SagFile {
         writeln!(output, "/* Auto-generated from SAG file */").unwrap();
         writeln!(output, "/* Config: {:?} */", config.name).unwrap();
         writeln!(output).unwrap();
-        writeln!(output, "OUTPUT_ARCH(riscv)").unwrap();
-        writeln!(output, "ENTRY(_start)").unwrap();
-        writeln!(output).unwrap();
+
+        // `riscv-rt`'s own `link.x` already declares `OUTPUT_ARCH`/`ENTRY`
+        // and `INCLUDE`s this script just for `MEMORY`/aliases, so
+        // forcing them here would conflict with it.
+        if config.flavor != LinkerFlavor::RiscvRt {
+            writeln!(output, "OUTPUT_ARCH(riscv)").unwrap();
+            writeln!(output, "ENTRY(_start)").unwrap();
+            writeln!(output).unwrap();
+        }
 
         // Memory regions
         writeln!(output, "MEMORY").unwrap();
@@ -421,17 +558,42 @@ impl SagFile {
         writeln!(output, "}}").unwrap();
         writeln!(output).unwrap();
 
+        if config.flavor == LinkerFlavor::RiscvRt {
+            emit_region_aliases(&mut output, config);
+        }
+
         // Stack symbol
+        let stack_symbol = stack_symbol_name(config.flavor);
         if let Some(stack) = self.find_stack() {
-            writeln!(output, "__stack_top = {:#010X};", stack).unwrap();
+            writeln!(output, "{} = {:#010X};", stack_symbol, stack).unwrap();
             writeln!(output).unwrap();
         }
 
+        // Stack-guard boundary (see LinkerScriptConfig::with_stack_guard)
+        match config.stack_guard_boundary {
+            Some(boundary) => {
+                writeln!(output, "_stack_start = {:#010X};", boundary).unwrap();
+                writeln!(output).unwrap();
+            }
+            // `riscv-rt` expects `_stack_start` even without a stack
+            // guard applied; alias it to the plain stack top.
+            None if config.flavor == LinkerFlavor::RiscvRt => {
+                if let Some(stack) = self.find_stack() {
+                    writeln!(output, "_stack_start = {:#010X};", stack).unwrap();
+                    writeln!(output).unwrap();
+                }
+            }
+            None => {}
+        }
+
         // Sections
         writeln!(output, "SECTIONS").unwrap();
         writeln!(output, "{{").unwrap();
 
+        self.emit_force_active(&mut output, config);
+
         let mut current_lma: u64 = 0;
+        let mut bss_bounded = false;
 
         for block in &self.blocks {
             let block_lma = block.lma.resolve(current_lma);
@@ -446,7 +608,7 @@ impl SagFile {
 
             for region in &block.regions {
                 let vma = region.vma.resolve(0);
-                self.emit_region(&mut output, region, current_lma, vma, config);
+                self.emit_region(&mut output, region, current_lma, vma, config, &mut bss_bounded);
             }
         }
 
@@ -459,6 +621,127 @@ impl SagFile {
         output
     }
 
+    /// Emit a `.force_active` output section that `KEEP`s every
+    /// `config.force_active` entry so `--gc-sections` can't discard it:
+    /// `.`-prefixed entries are treated as section patterns, bare entries
+    /// as symbols that must stay referenced. No-op when `force_active` is
+    /// empty, so output is unchanged for configs that don't use it.
+    fn emit_force_active(&self, output: &mut String, config: &LinkerScriptConfig) {
+        if config.force_active.is_empty() {
+            return;
+        }
+
+        let region = force_active_region(config);
+
+        writeln!(output, "    .force_active :").unwrap();
+        writeln!(output, "    {{").unwrap();
+        for name in &config.force_active {
+            if let Some(section) = name.strip_prefix('.') {
+                writeln!(output, "        KEEP(*(.{}))", section).unwrap();
+                writeln!(output, "        KEEP(*(.{}*))", section).unwrap();
+            } else {
+                writeln!(output, "        . = .;").unwrap();
+                writeln!(output, "        PROVIDE({} = .);", name).unwrap();
+            }
+        }
+        let fill_value = match config.flavor {
+            LinkerFlavor::Lld => None,
+            _ => config.fill_value,
+        };
+        match fill_value {
+            Some(fill) => writeln!(output, "    }} > {} =0x{:08X}", region, fill).unwrap(),
+            None => writeln!(output, "    }} > {}", region).unwrap(),
+        }
+
+        for name in &config.force_active {
+            if !name.starts_with('.') {
+                writeln!(
+                    output,
+                    "    ASSERT(DEFINED({0}), \"force_active: {0} was garbage collected\");",
+                    name
+                )
+                .unwrap();
+            }
+        }
+        writeln!(output).unwrap();
+    }
+
+    /// Produce a human-readable memory-map report: for every block/region
+    /// it lists the owning `MemoryRegion`, resolved VMA/LMA, whether it
+    /// runs in place (VMA region == LMA region), and every symbol
+    /// introduced by `ADDR`/`LOADADDR`/`STACK` with its resolved address
+    /// -- a quick way to eyeball placement (e.g. confirming
+    /// `__stack_top` and vector tables landed where expected) without
+    /// building and running `nm`/`objdump` on the final ELF.
+    ///
+    /// Reuses the exact `current_lma` alignment walk `to_linker_script`
+    /// uses, so the report always agrees with the generated script. Since
+    /// section sizes aren't known here either, an `ADDR`/`LOADADDR`
+    /// symbol is reported at its region's VMA/LMA rather than the exact
+    /// linker location counter at that point in the region.
+    pub fn to_map_report(&self, config: &LinkerScriptConfig) -> String {
+        let mut output = String::new();
+        let mut symbols: Vec<(String, u64)> = Vec::new();
+
+        writeln!(output, "Memory Map Report").unwrap();
+        writeln!(output, "Config: {}", config.name).unwrap();
+        writeln!(output).unwrap();
+        writeln!(
+            output,
+            "{:<16} {:<10} {:<12} {:<12} {:<3} {:<8}",
+            "REGION", "BLOCK", "VMA", "LMA", "RIP", "OWNER"
+        )
+        .unwrap();
+
+        let mut current_lma: u64 = 0;
+        for block in &self.blocks {
+            let block_lma = block.lma.resolve(current_lma);
+            current_lma = match block.alignment {
+                Some(align) => (block_lma + align - 1) & !(align - 1),
+                None => block_lma,
+            };
+
+            for region in &block.regions {
+                let vma = region.vma.resolve(0);
+                let lma = current_lma;
+                let runs_in_place = config.vma_to_region(vma) == config.vma_to_region(lma);
+                let owner = config.vma_to_region(vma).unwrap_or("?");
+
+                writeln!(
+                    output,
+                    "{:<16} {:<10} {:#010X}  {:#010X}  {:<3} {:<8}",
+                    region.name,
+                    block.block_type,
+                    vma,
+                    lma,
+                    if runs_in_place { "yes" } else { "no" },
+                    owner
+                )
+                .unwrap();
+
+                for directive in &region.directives {
+                    match directive {
+                        Directive::Addr { symbol, .. } => symbols.push((symbol.clone(), vma)),
+                        Directive::LoadAddr { symbol, .. } => symbols.push((symbol.clone(), lma)),
+                        Directive::Stack(addr) => {
+                            symbols.push((stack_symbol_name(config.flavor).to_string(), *addr))
+                        }
+                        Directive::Constant { symbol, value } => symbols.push((symbol.clone(), *value)),
+                        Directive::Section { .. } => {}
+                    }
+                }
+            }
+        }
+
+        writeln!(output).unwrap();
+        writeln!(output, "Symbols").unwrap();
+        for (name, addr) in &symbols {
+            writeln!(output, "  {:<28} {:#010X}", name, addr).unwrap();
+        }
+
+        output
+    }
+
     fn emit_region(
         &self,
         output: &mut String,
@@ -466,6 +749,7 @@ impl SagFile {
         lma: u64,
         vma: u64,
         config: &LinkerScriptConfig,
+        bss_bounded: &mut bool,
     ) {
         writeln!(output).unwrap();
         writeln!(output, "    /* Region: {} VMA={:#010X} LMA={:#010X} */", region.name, vma, lma).unwrap();
@@ -485,15 +769,44 @@ impl SagFile {
                     writeln!(output, "    {} = LOADADDR(.{});", symbol, region.name.to_lowercase()).unwrap();
                 }
                 Directive::Section { pattern, keep } => {
-                    let sections = self.expand_section_pattern(pattern);
+                    let sections = self.expand_section_pattern(pattern, config);
                     for section in sections {
                         let keep_str = if *keep { "KEEP" } else { "" };
-                        if runs_in_place {
-                            writeln!(output, "    .{} :", section).unwrap();
-                        } else {
-                            writeln!(output, "    .{} : AT({})", section, lma).unwrap();
+                        let load_region = config.section_load_regions.get(&section);
+                        let relocated = section == "data" && load_region.is_some();
+                        // Only bound the first `.bss`-like section: a SAG
+                        // layout can legitimately place `+ZI`/`.bss` in more
+                        // than one region, and a second `_sbss`/`_ebss` pair
+                        // would be a duplicate-symbol error at link time.
+                        let bounded_bss = section == "bss" && config.flavor == LinkerFlavor::BareMetal && !*bss_bounded;
+                        if bounded_bss {
+                            *bss_bounded = true;
+                        }
+                        // LLD's handling of the trailing `=fill` differs from
+                        // GNU ld's (see `LinkerFlavor::Lld`), so suppress it
+                        // under that flavor even if the config set one.
+                        let fill_value = match config.flavor {
+                            LinkerFlavor::Lld => None,
+                            _ => config.fill_value,
+                        };
+
+                        // GNU ld only accepts a parenthesized `AT(expr)` in
+                        // this pre-brace position -- `AT> REGION` is only
+                        // valid after the closing brace (see the
+                        // post-brace `match` below), so a symbolic load
+                        // region is expressed purely there instead.
+                        match load_region {
+                            Some(_) => writeln!(output, "    .{} :", section).unwrap(),
+                            None if runs_in_place => writeln!(output, "    .{} :", section).unwrap(),
+                            None => writeln!(output, "    .{} : AT({})", section, lma).unwrap(),
                         }
                         writeln!(output, "    {{").unwrap();
+                        if relocated {
+                            writeln!(output, "        _sdata = .;").unwrap();
+                        }
+                        if bounded_bss {
+                            writeln!(output, "        _sbss = .;").unwrap();
+                        }
                         if *keep {
                             writeln!(output, "        {}(*(.{}))", keep_str, section).unwrap();
                             writeln!(output, "        {}(*(.{}*))", keep_str, section).unwrap();
@@ -501,28 +814,52 @@ impl SagFile {
                             writeln!(output, "        *(.{})", section).unwrap();
                             writeln!(output, "        *(.{}*)", section).unwrap();
                         }
-                        writeln!(output, "    }} > {}", mem_region).unwrap();
+                        if relocated {
+                            writeln!(output, "        _edata = .;").unwrap();
+                        }
+                        if bounded_bss {
+                            writeln!(output, "        _ebss = .;").unwrap();
+                        }
+                        match (fill_value, load_region) {
+                            (Some(fill), Some(region)) => {
+                                writeln!(output, "    }} > {} AT> {} =0x{:08X}", mem_region, region, fill).unwrap()
+                            }
+                            (Some(fill), None) => writeln!(output, "    }} > {} =0x{:08X}", mem_region, fill).unwrap(),
+                            (None, Some(region)) => writeln!(output, "    }} > {} AT> {}", mem_region, region).unwrap(),
+                            (None, None) => writeln!(output, "    }} > {}", mem_region).unwrap(),
+                        }
+                        if relocated {
+                            writeln!(output, "    _sidata = LOADADDR(.{});", section).unwrap();
+                        }
                     }
                 }
                 Directive::Stack(addr) => {
-                    writeln!(output, "    __stack_top = {:#010X};", addr).unwrap();
+                    writeln!(output, "    {} = {:#010X};", stack_symbol_name(config.flavor), addr).unwrap();
+                }
+                Directive::Constant { symbol, value } => {
+                    writeln!(output, "    {} = {:#010X};", symbol, value).unwrap();
                 }
             }
         }
     }
 
-    fn expand_section_pattern(&self, pattern: &str) -> Vec<String> {
+    /// Expand a comma-separated section pattern (e.g. `+RO, .init`) into
+    /// concrete section names, consulting `config.section_groups` for
+    /// `+`-prefixed group tokens and falling back to the literal name
+    /// (stripped of a leading `.`) for anything the config doesn't know
+    /// about. This lets a project redefine or add groups (e.g. `+FAST` for
+    /// itcm sections) without patching the crate.
+    fn expand_section_pattern(&self, pattern: &str, config: &LinkerScriptConfig) -> Vec<String> {
         let mut sections = Vec::new();
 
         for part in pattern.split(',') {
             let part = part.trim();
-            match part {
-                "+ISR" => sections.extend(["vectors", "isr"].map(String::from)),
-                "+RO" => sections.extend(["text", "rodata", "srodata"].map(String::from)),
-                "+RW" => sections.extend(["data", "sdata"].map(String::from)),
-                "+ZI" => sections.extend(["bss", "sbss"].map(String::from)),
-                s if s.starts_with('.') => sections.push(s[1..].to_string()),
-                s => sections.push(s.to_string()),
+            if let Some(group) = config.section_groups.get(part) {
+                sections.extend(group.iter().cloned());
+            } else if let Some(stripped) = part.strip_prefix('.') {
+                sections.push(stripped.to_string());
+            } else {
+                sections.push(part.to_string());
             }
         }
 
@@ -541,6 +878,274 @@ impl SagFile {
         }
         None
     }
+
+    /// Check the layout `to_linker_script` would emit against
+    /// `config.memory_regions`, catching the ways a generated script can
+    /// be silently broken: a region placed outside every configured
+    /// `MemoryRegion`, two regions overlapping in the same memory region,
+    /// or a writable region mapped into a read/execute-only one (e.g.
+    /// `FLASH`).
+    ///
+    /// This reproduces the exact `current_lma` alignment walk
+    /// `to_linker_script` uses, so a layout that validates here is the
+    /// same layout that gets emitted. Section sizes aren't known from the
+    /// SAG file alone, so each placed section is treated as a fixed
+    /// symbolic unit (`SYMBOLIC_SECTION_SIZE` bytes) for the
+    /// overlap/bounds checks; the writable-into-`rx` attribute check is
+    /// the one part of this pass that is exact regardless of size.
+    pub fn validate(&self, config: &LinkerScriptConfig) -> Result<(), Vec<LayoutError>> {
+        let mut errors = Vec::new();
+        let mut placed = Vec::new();
+
+        let mut current_lma: u64 = 0;
+        for block in &self.blocks {
+            let block_lma = block.lma.resolve(current_lma);
+            current_lma = match block.alignment {
+                Some(align) => (block_lma + align - 1) & !(align - 1),
+                None => block_lma,
+            };
+
+            for region in &block.regions {
+                let vma = region.vma.resolve(0);
+                let size = self.region_symbolic_size(region, config);
+                placed.push(PlacedRegion {
+                    name: &region.name,
+                    vma,
+                    lma: current_lma,
+                    size,
+                    writable: self.region_is_writable(region, config),
+                });
+            }
+        }
+
+        for p in &placed {
+            self.check_bounds(config, p, &mut errors);
+        }
+        self.check_overlaps(config, &placed, &mut errors);
+        self.check_writable_attributes(config, &placed, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check_bounds(&self, config: &LinkerScriptConfig, p: &PlacedRegion, errors: &mut Vec<LayoutError>) {
+        if !config.memory_regions.values().any(|r| interval_contains(r, p.vma, p.size)) {
+            errors.push(LayoutError::VmaOutOfBounds {
+                region: p.name.to_string(),
+                start: p.vma,
+                end: p.vma + p.size,
+            });
+        }
+        if !config.memory_regions.values().any(|r| interval_contains(r, p.lma, p.size)) {
+            errors.push(LayoutError::LmaOutOfBounds {
+                region: p.name.to_string(),
+                start: p.lma,
+                end: p.lma + p.size,
+            });
+        }
+    }
+
+    fn check_overlaps(&self, config: &LinkerScriptConfig, placed: &[PlacedRegion], errors: &mut Vec<LayoutError>) {
+        for (i, a) in placed.iter().enumerate() {
+            for b in &placed[i + 1..] {
+                let Some(a_region) = config.vma_to_region(a.vma) else {
+                    continue;
+                };
+                if config.vma_to_region(b.vma) != Some(a_region) {
+                    continue;
+                }
+                if a.vma < b.vma + b.size && b.vma < a.vma + a.size {
+                    errors.push(LayoutError::Overlap {
+                        mem_region: a_region.to_string(),
+                        first: a.name.to_string(),
+                        first_start: a.vma,
+                        first_end: a.vma + a.size,
+                        second: b.name.to_string(),
+                        second_start: b.vma,
+                        second_end: b.vma + b.size,
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_writable_attributes(
+        &self,
+        config: &LinkerScriptConfig,
+        placed: &[PlacedRegion],
+        errors: &mut Vec<LayoutError>,
+    ) {
+        for p in placed {
+            if !p.writable {
+                continue;
+            }
+            let Some(mem_region_name) = config.vma_to_region(p.vma) else {
+                continue;
+            };
+            let attributes = &config.memory_regions[mem_region_name].attributes;
+            if !attributes.contains('w') {
+                errors.push(LayoutError::WritableIntoReadOnly {
+                    region: p.name.to_string(),
+                    mem_region: mem_region_name.to_string(),
+                    attributes: attributes.clone(),
+                });
+            }
+        }
+    }
+
+    /// Symbolic size of a region for layout-validation purposes: each
+    /// section it places counts as one `SYMBOLIC_SECTION_SIZE`-byte unit,
+    /// since real sizes aren't known until link time.
+    fn region_symbolic_size(&self, region: &Region, config: &LinkerScriptConfig) -> u64 {
+        region
+            .directives
+            .iter()
+            .filter_map(|d| match d {
+                Directive::Section { pattern, .. } => {
+                    Some(self.expand_section_pattern(pattern, config).len() as u64)
+                }
+                _ => None,
+            })
+            .sum::<u64>()
+            * SYMBOLIC_SECTION_SIZE
+    }
+
+    /// A region is writable if any section it places expands to a
+    /// data/bss name (i.e. came from `+RW`/`+ZI` or a literal
+    /// `.data`/`.bss`-style section). Checked by exact name rather than
+    /// substring, since e.g. `rodata` must not match `data`.
+    fn region_is_writable(&self, region: &Region, config: &LinkerScriptConfig) -> bool {
+        const WRITABLE_SECTIONS: [&str; 4] = ["data", "sdata", "bss", "sbss"];
+        region.directives.iter().any(|d| match d {
+            Directive::Section { pattern, .. } => self
+                .expand_section_pattern(pattern, config)
+                .iter()
+                .any(|s| WRITABLE_SECTIONS.contains(&s.as_str())),
+            _ => false,
+        })
+    }
+}
+
+/// Fixed symbolic size (in bytes) attributed to each section placed by a
+/// region, used by [`SagFile::validate`] when the real linked size isn't
+/// available.
+const SYMBOLIC_SECTION_SIZE: u64 = 4;
+
+/// A region resolved to concrete addresses, ready for layout validation.
+struct PlacedRegion<'a> {
+    name: &'a str,
+    vma: u64,
+    lma: u64,
+    size: u64,
+    writable: bool,
+}
+
+fn interval_contains(region: &MemoryRegion, start: u64, size: u64) -> bool {
+    start >= region.origin && start + size <= region.origin + region.length
+}
+
+/// Pick the memory region to place `.force_active` in: vectors/handlers
+/// are the usual force-kept content, so prefer a read-execute-only region
+/// (typically flash) over a writable/executable one, then fall back to
+/// any executable region, then to whatever sorts first by name, all ties
+/// broken alphabetically for determinism.
+fn force_active_region(config: &LinkerScriptConfig) -> &str {
+    let rx_only = config
+        .memory_regions
+        .iter()
+        .filter(|(_, r)| r.attributes.contains('x') && !r.attributes.contains('w'))
+        .map(|(name, _)| name.as_str())
+        .min();
+
+    rx_only
+        .or_else(|| {
+            config
+                .memory_regions
+                .iter()
+                .filter(|(_, r)| r.attributes.contains('x'))
+                .map(|(name, _)| name.as_str())
+                .min()
+        })
+        .or_else(|| config.memory_regions.keys().map(|s| s.as_str()).min())
+        .unwrap_or("RAM")
+}
+
+/// Selects the downstream toolchain/runtime `to_linker_script` targets,
+/// analogous to rustc's linker flavors: the same parsed SAG can drive
+/// very different conventions for region-alias names, the stack symbol,
+/// and whether the script is expected to stand alone or be `INCLUDE`d by
+/// a runtime crate's own script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkerFlavor {
+    /// Plain GNU ld output: always forces the `OUTPUT_ARCH`/`ENTRY`
+    /// preamble, emits no `REGION_ALIAS`es, and names the stack symbol
+    /// `__stack_top`. Matches every config's behavior before flavors
+    /// existed.
+    #[default]
+    GnuLd,
+    /// Like `GnuLd`, but skips the `=FILL` suffix `fill_value` would
+    /// otherwise add to every output section: some LLD versions parse
+    /// the trailing `=fill` after `> REGION` differently than GNU ld, so
+    /// until that's verified across every target this flavor supports,
+    /// leave fill value unset rather than risk a silent mismatch.
+    Lld,
+    /// `riscv-rt`-compatible: emits `REGION_TEXT`/`REGION_RODATA`/
+    /// `REGION_DATA`/`REGION_BSS`/`REGION_HEAP`/`REGION_STACK` aliases
+    /// and a `_stack_start` symbol, and skips the `OUTPUT_ARCH`/`ENTRY`
+    /// preamble since riscv-rt's own `link.x` declares those and
+    /// `INCLUDE`s this script purely for `MEMORY`/aliases.
+    RiscvRt,
+    /// A bare-metal convention distinct from riscv-rt: the stack symbol
+    /// is `__stack` instead of `__stack_top`, and the `.bss` output
+    /// section additionally gets `_sbss`/`_ebss` bounds for a startup
+    /// routine to zero.
+    BareMetal,
+}
+
+/// The symbol name `to_linker_script`/`to_map_report` use for the stack
+/// top; every flavor but `BareMetal` uses the crate's long-standing
+/// `__stack_top`.
+fn stack_symbol_name(flavor: LinkerFlavor) -> &'static str {
+    match flavor {
+        LinkerFlavor::BareMetal => "__stack",
+        LinkerFlavor::GnuLd | LinkerFlavor::Lld | LinkerFlavor::RiscvRt => "__stack_top",
+    }
+}
+
+/// Picks the RAM-like region `riscv-rt`'s `REGION_DATA`/`REGION_BSS`/
+/// `REGION_HEAP`/`REGION_STACK` aliases should point at: prefer a
+/// writable region, falling back to whatever sorts first by name, ties
+/// broken alphabetically -- mirrors `force_active_region`'s style since
+/// both are picking "the region that plausibly holds this content".
+fn riscv_rt_ram_region(config: &LinkerScriptConfig) -> &str {
+    config
+        .memory_regions
+        .iter()
+        .filter(|(_, r)| r.attributes.contains('w'))
+        .map(|(name, _)| name.as_str())
+        .min()
+        .or_else(|| config.memory_regions.keys().map(|s| s.as_str()).min())
+        .unwrap_or("RAM")
+}
+
+/// Emit the `REGION_ALIAS` calls `riscv-rt`'s `link.x` expects from an
+/// included memory script: code/read-only aliases point at
+/// `force_active_region`'s pick (the same rx-preferred region vectors and
+/// handlers get kept in), and the writable aliases point at
+/// `riscv_rt_ram_region`'s pick.
+fn emit_region_aliases(output: &mut String, config: &LinkerScriptConfig) {
+    let code_region = force_active_region(config);
+    let ram_region = riscv_rt_ram_region(config);
+    for alias in ["REGION_TEXT", "REGION_RODATA"] {
+        writeln!(output, "REGION_ALIAS(\"{}\", {});", alias, code_region).unwrap();
+    }
+    for alias in ["REGION_DATA", "REGION_BSS", "REGION_HEAP", "REGION_STACK"] {
+        writeln!(output, "REGION_ALIAS(\"{}\", {});", alias, ram_region).unwrap();
+    }
+    writeln!(output).unwrap();
 }
 
 /// Memory region configuration
@@ -556,6 +1161,55 @@ pub struct MemoryRegion {
 pub struct LinkerScriptConfig {
     pub name: String,
     pub memory_regions: HashMap<String, MemoryRegion>,
+    /// Maps a `+`-prefixed section-group token (as used in SAG `* ( ... )`
+    /// patterns) to the concrete section names it expands to, e.g. `+RO`
+    /// -> `["text", "rodata", "srodata"]`. Lets a project add groups (like
+    /// `+FAST` for itcm sections) or redefine the defaults without
+    /// patching the crate.
+    pub section_groups: HashMap<String, Vec<String>>,
+    /// Section patterns (leading `.`) or bare symbols that must survive
+    /// `--gc-sections`, emitted as `KEEP`s (or `PROVIDE`d/`ASSERT`ed, for
+    /// symbols) in a dedicated `.force_active` output section. Empty by
+    /// default, which is a no-op.
+    pub force_active: Vec<String>,
+    /// When set, emitted as a `=0xNNNNNNNN` fill expression on every
+    /// generated output section, so alignment padding and inter-region
+    /// gaps are deterministically filled (e.g. to match a flash erase
+    /// state) instead of left undefined. `None` is a no-op.
+    pub fill_value: Option<u32>,
+    /// Set by [`LinkerScriptConfig::with_stack_guard`]; when present,
+    /// `to_linker_script` emits `_stack_start` at this address. Not meant
+    /// to be set directly.
+    pub stack_guard_boundary: Option<u64>,
+    /// Maps an (expanded, dotless) section name to the memory region
+    /// holding its load image, for sections whose run location (VMA)
+    /// differs from where their initial contents live (LMA) -- e.g. an
+    /// execute-in-place config's `.data`, which runs from RAM but loads
+    /// from FLASH. When a section has an entry here, `to_linker_script`
+    /// emits a symbolic `AT> REGION` instead of deriving the LMA
+    /// numerically from the SAG file's block placement, and for `.data`
+    /// specifically also emits the `_sidata`/`_sdata`/`_edata` symbols a
+    /// runtime needs to copy it out of flash at startup. Empty by
+    /// default, which is a no-op (every DDR/ILM section just assumes
+    /// LMA == VMA).
+    pub section_load_regions: HashMap<String, String>,
+    /// Which downstream toolchain/runtime convention `to_linker_script`
+    /// targets. Defaults to [`LinkerFlavor::GnuLd`], matching every
+    /// preset's behavior before flavors existed.
+    pub flavor: LinkerFlavor,
+}
+
+/// The section-group expansions used by the built-in AE350 presets.
+fn default_section_groups() -> HashMap<String, Vec<String>> {
+    let mut groups = HashMap::new();
+    groups.insert("+ISR".to_string(), vec!["vectors".to_string(), "isr".to_string()]);
+    groups.insert(
+        "+RO".to_string(),
+        vec!["text".to_string(), "rodata".to_string(), "srodata".to_string()],
+    );
+    groups.insert("+RW".to_string(), vec!["data".to_string(), "sdata".to_string()]);
+    groups.insert("+ZI".to_string(), vec!["bss".to_string(), "sbss".to_string()]);
+    groups
 }
 
 impl LinkerScriptConfig {
@@ -584,6 +1238,12 @@ impl LinkerScriptConfig {
         Self {
             name: "AE350 DDR".to_string(),
             memory_regions,
+            section_groups: default_section_groups(),
+            force_active: Vec::new(),
+            fill_value: None,
+            stack_guard_boundary: None,
+            section_load_regions: HashMap::new(),
+            flavor: LinkerFlavor::GnuLd,
         }
     }
 
@@ -612,6 +1272,54 @@ impl LinkerScriptConfig {
         Self {
             name: "AE350 ILM".to_string(),
             memory_regions,
+            section_groups: default_section_groups(),
+            force_active: Vec::new(),
+            fill_value: None,
+            stack_guard_boundary: None,
+            section_load_regions: HashMap::new(),
+            flavor: LinkerFlavor::GnuLd,
+        }
+    }
+
+    /// Create a config for AE350 execute-in-place (XIP) mode: `.text`
+    /// and read-only data stay resident in FLASH (LMA == VMA, like the
+    /// DDR/ILM presets), while `.data` is built with its load image in
+    /// FLASH but runs from DDR, relocated at startup via the
+    /// auto-emitted `_sidata`/`_sdata`/`_edata` symbols (see
+    /// `section_load_regions`). `.bss` runs from DDR with no load image.
+    pub fn ae350_xip() -> Self {
+        let mut memory_regions = HashMap::new();
+
+        memory_regions.insert(
+            "FLASH".to_string(),
+            MemoryRegion {
+                origin: 0x80000000,
+                length: 256 * 1024 * 1024,
+                attributes: "rx".to_string(),
+            },
+        );
+
+        memory_regions.insert(
+            "DDR".to_string(),
+            MemoryRegion {
+                origin: 0x00000000,
+                length: 128 * 1024 * 1024,
+                attributes: "rwx".to_string(),
+            },
+        );
+
+        let mut section_load_regions = HashMap::new();
+        section_load_regions.insert("data".to_string(), "FLASH".to_string());
+
+        Self {
+            name: "AE350 XIP".to_string(),
+            memory_regions,
+            section_groups: default_section_groups(),
+            force_active: Vec::new(),
+            fill_value: None,
+            stack_guard_boundary: None,
+            section_load_regions,
+            flavor: LinkerFlavor::GnuLd,
         }
     }
 
@@ -623,6 +1331,109 @@ impl LinkerScriptConfig {
         }
         None
     }
+
+    /// Apply flip-link-style stack-overflow protection: relocate
+    /// `ram_region` so its statics sit at the *top* of RAM (origin
+    /// becomes `ORIGIN + LENGTH - static_size`, length shrinks to
+    /// `static_size`) and record that boundary as `_stack_start`, leaving
+    /// the downward-growing stack to occupy the space below it. An
+    /// overflow then runs off the bottom of RAM and faults instead of
+    /// silently corrupting `.data`/`.bss`.
+    ///
+    /// `static_size` -- the combined size of every output section placed
+    /// in `ram_region` -- isn't known until link time, so this is meant
+    /// to be applied between the two passes of a stack-guarded build:
+    /// link once with the unmodified config, measure the resulting ELF
+    /// with [`measure_region_usage`], then regenerate and re-link with
+    /// the config this returns.
+    ///
+    /// Returns `Err(SagError::InvalidAddress(..))` if `static_size` is
+    /// larger than `ram_region` itself -- a measured ELF shouldn't
+    /// produce that, but a stack guard pointed at the wrong (or
+    /// genuinely full) region shouldn't panic either.
+    pub fn with_stack_guard(&self, ram_region: &str, static_size: u64) -> Result<Self, SagError> {
+        let mut config = self.clone();
+        if let Some(region) = config.memory_regions.get_mut(ram_region) {
+            let boundary = region
+                .length
+                .checked_sub(static_size)
+                .and_then(|free| region.origin.checked_add(free))
+                .ok_or_else(|| {
+                    SagError::InvalidAddress(format!(
+                        "stack guard: {} bytes in use doesn't fit in region '{}' (origin {:#010X}, length {})",
+                        static_size, ram_region, region.origin, region.length
+                    ))
+                })?;
+            region.origin = boundary;
+            region.length = static_size;
+            config.stack_guard_boundary = Some(boundary);
+        }
+        Ok(config)
+    }
+}
+
+/// Measure the combined size (in bytes) of every `SHF_ALLOC` ELF section
+/// whose virtual address falls inside `region`, i.e. the runtime
+/// footprint of whatever got placed there. Used to compute `static_size`
+/// for [`LinkerScriptConfig::with_stack_guard`] between the two passes of
+/// a stack-guarded build. Only 64-bit little-endian ELF (as produced by
+/// `riscv64-*` toolchains) is supported.
+pub fn measure_region_usage(elf_bytes: &[u8], region: &MemoryRegion) -> Result<u64, SagError> {
+    const EHDR_SIZE: usize = 64;
+    const SHF_ALLOC: u64 = 0x2;
+
+    if elf_bytes.len() < EHDR_SIZE || elf_bytes[0..4] != [0x7f, b'E', b'L', b'F'] {
+        return Err(SagError::Elf("not an ELF file".to_string()));
+    }
+    if elf_bytes[4] != 2 {
+        return Err(SagError::Elf("only 64-bit ELF is supported".to_string()));
+    }
+    if elf_bytes[5] != 1 {
+        return Err(SagError::Elf("only little-endian ELF is supported".to_string()));
+    }
+
+    let read_u16 = |off: usize| -> Result<u16, SagError> {
+        elf_bytes
+            .get(off..off + 2)
+            .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+            .ok_or_else(|| SagError::Elf("truncated ELF header".to_string()))
+    };
+    let read_u64 = |off: usize| -> Result<u64, SagError> {
+        elf_bytes
+            .get(off..off + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            .ok_or_else(|| SagError::Elf("truncated ELF header".to_string()))
+    };
+
+    let e_shoff = read_u64(40)? as usize;
+    let e_shentsize = read_u16(58)? as usize;
+    let e_shnum = read_u16(60)? as usize;
+
+    let mut total = 0u64;
+    for i in 0..e_shnum {
+        let base = i
+            .checked_mul(e_shentsize)
+            .and_then(|offset| e_shoff.checked_add(offset))
+            .ok_or_else(|| SagError::Elf("section header table offset overflow".to_string()))?;
+        let end = base
+            .checked_add(e_shentsize)
+            .ok_or_else(|| SagError::Elf("section header table offset overflow".to_string()))?;
+        if end > elf_bytes.len() {
+            return Err(SagError::Elf("section header table out of bounds".to_string()));
+        }
+        let sh_flags = read_u64(base + 8)?;
+        let sh_addr = read_u64(base + 16)?;
+        let sh_size = read_u64(base + 32)?;
+
+        if sh_flags & SHF_ALLOC == 0 || sh_size == 0 {
+            continue;
+        }
+        if sh_addr >= region.origin && sh_addr < region.origin + region.length {
+            total += sh_size;
+        }
+    }
+
+    Ok(total)
 }
 
 fn format_size(bytes: u64) -> String {
@@ -637,34 +1448,1215 @@ fn format_size(bytes: u64) -> String {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// A single `SECTIONS` output-section entry as imported by
+/// [`LinkerScript::parse`]: its name and the input-section patterns
+/// placed into it (with whether each survived via `KEEP`). Where it's
+/// placed (`> REGION`), its LMA (`AT(...)`/`AT>`), and any fill value are
+/// parsed and discarded -- that's [`LinkerScriptConfig`]'s job on the way
+/// back out, not [`SagFile`]'s.
+#[derive(Debug, Clone, Default)]
+pub struct LinkerScriptSection {
+    pub name: String,
+    pub inputs: Vec<(String, bool)>,
+}
 
-    #[test]
-    fn test_parse_address() {
-        assert!(matches!(Address::parse("0x80000000").unwrap(), Address::Absolute(0x80000000)));
-        assert!(matches!(Address::parse("+0").unwrap(), Address::Relative(0)));
-        assert!(matches!(Address::parse("+256").unwrap(), Address::Relative(256)));
+/// The value on the right-hand side of a linker-script symbol
+/// assignment (`NAME = ...;` or `PROVIDE(NAME = ...);`). `Location` and
+/// `LoadAddrOf` stand for expressions this offline parser can't actually
+/// evaluate -- both require knowing where the linker placed other
+/// sections, which only the linker itself knows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkerScriptSymbolValue {
+    /// `NAME = .;` -- the current location counter.
+    Location,
+    /// `NAME = LOADADDR(.section);`
+    LoadAddrOf(String),
+    /// `NAME = <constant expression>;`
+    Constant(u64),
+}
+
+/// AST for an imported GNU LD linker script (`.x`/`.ld`). Produced by
+/// [`LinkerScript::parse`] when reading a hand-written or previously
+/// `sag2ld`-generated script, complementing `SagFile::to_linker_script`'s
+/// generation direction. [`LinkerScript::to_sag_file`] converts the
+/// placement half of this AST into the same [`SagFile`] shape the SAG
+/// parser produces, so `sag2ld --print-ast` works on either input format
+/// and a SAG-generated script can be diffed against a vendor-provided
+/// one.
+#[derive(Debug, Clone, Default)]
+pub struct LinkerScript {
+    pub output_format: Option<String>,
+    pub output_arch: Option<String>,
+    pub entry: Option<String>,
+    pub memory_regions: HashMap<String, MemoryRegion>,
+    pub region_aliases: HashMap<String, String>,
+    pub inputs: Vec<String>,
+    pub symbols: Vec<(String, LinkerScriptSymbolValue)>,
+    pub sections: Vec<LinkerScriptSection>,
+}
+
+impl LinkerScript {
+    /// Parse a GNU LD linker script, supporting the core grammar:
+    /// `MEMORY` (with `K`/`M`/`G`-suffixed lengths and `+`/`-`/`*`
+    /// arithmetic), `REGION_ALIAS`, top-level and `SECTIONS`-level symbol
+    /// assignments (plain or `PROVIDE`d), and
+    /// `SECTIONS`/`INPUT`/`GROUP`/`OUTPUT_FORMAT`/`OUTPUT_ARCH`/`ENTRY`.
+    /// Comments and nested expressions are tolerated; any other
+    /// top-level or `SECTIONS`-level directive (`ASSERT`, `ALIGN`, `.`
+    /// advances, etc.) is skipped up to its closing `;` so one
+    /// unsupported construct doesn't block parsing the rest.
+    pub fn parse(content: &str) -> Result<Self, SagError> {
+        let mut stream = TokenStream::new(tokenize(content));
+        let mut script = LinkerScript::default();
+
+        while let Some(tok) = stream.peek().map(str::to_string) {
+            match tok.as_str() {
+                "MEMORY" => {
+                    stream.next();
+                    parse_memory(&mut stream, &mut script)?;
+                }
+                "REGION_ALIAS" => {
+                    stream.next();
+                    parse_region_alias(&mut stream, &mut script)?;
+                }
+                "OUTPUT_FORMAT" => {
+                    stream.next();
+                    script.output_format = parse_call_arg(&mut stream)?;
+                }
+                "OUTPUT_ARCH" => {
+                    stream.next();
+                    script.output_arch = parse_call_arg(&mut stream)?;
+                }
+                "ENTRY" => {
+                    stream.next();
+                    script.entry = parse_call_arg(&mut stream)?;
+                }
+                "INPUT" | "GROUP" => {
+                    stream.next();
+                    script.inputs.extend(parse_file_list(&mut stream)?);
+                }
+                "SECTIONS" => {
+                    stream.next();
+                    parse_sections(&mut stream, &mut script)?;
+                }
+                "PROVIDE" => {
+                    stream.next();
+                    parse_provide(&mut stream, &mut script)?;
+                }
+                ";" => {
+                    stream.next();
+                }
+                _ => parse_symbol_or_skip(&mut stream, &mut script.symbols)?,
+            }
+        }
+
+        Ok(script)
     }
 
-    #[test]
-    fn test_parse_simple_sag() {
-        let content = r#"
-USER_SECTIONS .bootloader
+    /// Convert the placement half of this AST into the same [`SagFile`]
+    /// shape the SAG parser produces, so `sag2ld --print-ast` can be
+    /// pointed at either format. Necessarily lossy: GNU LD resolves most
+    /// output-section addresses from the location counter at link time,
+    /// which this offline converter can't evaluate, so every section's
+    /// VMA comes back as a placeholder of `0`. `MEMORY`/`REGION_ALIAS`
+    /// stay on `self` rather than feeding into the result, matching the
+    /// crate's existing split between `SagFile` (layout) and
+    /// [`LinkerScriptConfig`] (physical memory).
+    pub fn to_sag_file(&self) -> SagFile {
+        let mut regions: Vec<Region> = self
+            .sections
+            .iter()
+            .map(|section| Region {
+                name: section.name.trim_start_matches('.').to_string(),
+                vma: Address::Absolute(0),
+                directives: section
+                    .inputs
+                    .iter()
+                    .map(|(pattern, keep)| Directive::Section { pattern: pattern.clone(), keep: *keep })
+                    .collect(),
+            })
+            .collect();
+
+        // Stack symbol names recognized across every `LinkerFlavor` (see
+        // `stack_symbol_name`): a constant assigned to one of these is
+        // almost certainly the stack pointer, so it round-trips as
+        // `Directive::Stack` rather than a generic named constant.
+        const STACK_SYMBOL_NAMES: [&str; 2] = ["__stack_top", "__stack"];
+
+        for (name, value) in &self.symbols {
+            let directive = match value {
+                LinkerScriptSymbolValue::Location => Directive::Addr { symbol: name.clone(), next: false },
+                LinkerScriptSymbolValue::LoadAddrOf(_) => Directive::LoadAddr { symbol: name.clone(), next: false },
+                LinkerScriptSymbolValue::Constant(value) if STACK_SYMBOL_NAMES.contains(&name.as_str()) => {
+                    Directive::Stack(*value)
+                }
+                LinkerScriptSymbolValue::Constant(value) => {
+                    Directive::Constant { symbol: name.clone(), value: *value }
+                }
+            };
+            regions.push(Region {
+                name: name.clone(),
+                vma: Address::Absolute(0),
+                directives: vec![directive],
+            });
+        }
 
-HEAD 0x00000000
-{
-    BOOTLOADER 0x80000000
-    {
-        ADDR __flash_start
-        * KEEP ( .bootloader )
+        SagFile {
+            user_sections: Vec::new(),
+            blocks: vec![Block {
+                block_type: "IMPORTED".to_string(),
+                lma: Address::Absolute(0),
+                alignment: None,
+                regions,
+            }],
+        }
     }
 }
-"#;
-        let sag = SagFile::parse(content).unwrap();
-        assert_eq!(sag.user_sections.len(), 1);
-        assert_eq!(sag.blocks.len(), 1);
-        assert_eq!(sag.blocks[0].regions.len(), 1);
+
+/// Token stream for [`LinkerScript::parse`], pairing each token with the
+/// source line it started on for error messages.
+struct TokenStream {
+    tokens: Vec<(String, usize)>,
+    pos: usize,
+}
+
+impl TokenStream {
+    fn new(tokens: Vec<(String, usize)>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|(t, _)| t.as_str())
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).map(|(_, l)| *l).unwrap_or(0)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let tok = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        if tok.is_some() {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), SagError> {
+        let line = self.line();
+        match self.next() {
+            Some(tok) if tok == expected => Ok(()),
+            Some(tok) => Err(SagError::Parse {
+                line,
+                message: format!("expected '{}', found '{}'", expected, tok),
+            }),
+            None => Err(SagError::Parse {
+                line,
+                message: format!("expected '{}', found end of file", expected),
+            }),
+        }
+    }
+
+    fn consume_optional(&mut self, tok: &str) {
+        if self.peek() == Some(tok) {
+            self.next();
+        }
+    }
+}
+
+fn eof_error(stream: &TokenStream, message: impl Into<String>) -> SagError {
+    SagError::Parse { line: stream.line(), message: message.into() }
+}
+
+/// Strip `/* ... */` comments (the only comment form GNU LD scripts
+/// support), preserving newlines inside them so token line numbers stay
+/// accurate.
+fn strip_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut prev = ' ';
+            for ch in chars.by_ref() {
+                if ch == '\n' {
+                    out.push('\n');
+                }
+                if prev == '*' && ch == '/' {
+                    break;
+                }
+                prev = ch;
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Split a linker script into tokens, splitting on whitespace and on
+/// `{ } ( ) , ; = : > + - *` as single-character tokens each; everything
+/// else (identifiers, section names like `.text`, numbers with `K`/`M`/`G`
+/// suffixes) accumulates as one word token. Quoted strings are kept
+/// whole (quotes included) so callers can tell them apart from bare
+/// identifiers.
+fn tokenize(content: &str) -> Vec<(String, usize)> {
+    const PUNCTUATION: &str = "{}(),;=:>+-*";
+
+    let stripped = strip_comments(content);
+    let mut tokens = Vec::new();
+    let mut chars = stripped.chars().peekable();
+    let mut line = 1usize;
+
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            line += 1;
+            chars.next();
+        } else if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            let start_line = line;
+            chars.next();
+            let mut s = String::new();
+            for ch in chars.by_ref() {
+                if ch == '"' {
+                    break;
+                }
+                if ch == '\n' {
+                    line += 1;
+                }
+                s.push(ch);
+            }
+            tokens.push((format!("\"{}\"", s), start_line));
+        } else if PUNCTUATION.contains(c) {
+            tokens.push((c.to_string(), line));
+            chars.next();
+        } else {
+            let start_line = line;
+            let mut s = String::new();
+            while let Some(&ch) = chars.peek() {
+                if ch.is_whitespace() || ch == '"' || PUNCTUATION.contains(ch) {
+                    break;
+                }
+                s.push(ch);
+                chars.next();
+            }
+            tokens.push((s, start_line));
+        }
+    }
+
+    tokens
+}
+
+/// Parse a `K`/`M`/`G`-suffixed or hex/decimal literal, as used in
+/// `ORIGIN`/`LENGTH` expressions (e.g. `256M`, `0x80000000`).
+fn parse_number(tok: &str) -> Result<u64, SagError> {
+    let upper = tok.to_ascii_uppercase();
+    let (digits, multiplier) = if let Some(stripped) = upper.strip_suffix('G') {
+        (stripped, 1024 * 1024 * 1024)
+    } else if let Some(stripped) = upper.strip_suffix('M') {
+        (stripped, 1024 * 1024)
+    } else if let Some(stripped) = upper.strip_suffix('K') {
+        (stripped, 1024)
+    } else {
+        (upper.as_str(), 1)
+    };
+
+    let value = if let Some(hex) = digits.strip_prefix("0X") {
+        u64::from_str_radix(hex, 16)
+    } else {
+        digits.parse::<u64>()
+    }
+    .map_err(|_| SagError::InvalidAddress(tok.to_string()))?;
+
+    Ok(value * multiplier)
+}
+
+fn parse_term(stream: &mut TokenStream) -> Result<u64, SagError> {
+    match stream.next() {
+        Some(tok) if tok == "(" => {
+            let value = parse_expr(stream)?;
+            stream.expect(")")?;
+            Ok(value)
+        }
+        Some(tok) => parse_number(&tok),
+        None => Err(eof_error(stream, "expected an expression")),
+    }
+}
+
+/// Simple left-to-right `+`/`-`/`*` arithmetic over [`parse_term`]s, as
+/// used in `ORIGIN`/`LENGTH` expressions (e.g. `0x80000000 + 16K`).
+fn parse_expr(stream: &mut TokenStream) -> Result<u64, SagError> {
+    let mut value = parse_term(stream)?;
+    loop {
+        match stream.peek() {
+            Some("+") => {
+                stream.next();
+                value += parse_term(stream)?;
+            }
+            Some("-") => {
+                stream.next();
+                value -= parse_term(stream)?;
+            }
+            Some("*") => {
+                stream.next();
+                value *= parse_term(stream)?;
+            }
+            _ => break,
+        }
+    }
+    Ok(value)
+}
+
+fn skip_balanced_parens(stream: &mut TokenStream) -> Result<(), SagError> {
+    stream.expect("(")?;
+    let mut depth = 1;
+    while depth > 0 {
+        match stream.next() {
+            Some(t) if t == "(" => depth += 1,
+            Some(t) if t == ")" => depth -= 1,
+            Some(_) => {}
+            None => return Err(eof_error(stream, "unterminated parenthesized expression")),
+        }
+    }
+    Ok(())
+}
+
+/// Skip an unrecognized directive up to its closing `;`, or up to (and
+/// including) a balanced `{ ... }` if it opens one instead.
+fn skip_directive(stream: &mut TokenStream) -> Result<(), SagError> {
+    let mut depth = 0;
+    loop {
+        match stream.next() {
+            Some(t) if t == "{" => depth += 1,
+            Some(t) if t == "}" && depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Some(t) if t == ";" && depth == 0 => break,
+            Some(t) if t == "}" && depth == 0 => break,
+            Some(_) => {}
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+fn parse_call_arg(stream: &mut TokenStream) -> Result<Option<String>, SagError> {
+    stream.expect("(")?;
+    let arg = stream.next().map(|t| t.trim_matches('"').to_string());
+    let mut depth = 1;
+    while depth > 0 {
+        match stream.next() {
+            Some(t) if t == "(" => depth += 1,
+            Some(t) if t == ")" => depth -= 1,
+            Some(_) => {}
+            None => break,
+        }
+    }
+    stream.consume_optional(";");
+    Ok(arg)
+}
+
+fn parse_file_list(stream: &mut TokenStream) -> Result<Vec<String>, SagError> {
+    stream.expect("(")?;
+    let mut files = Vec::new();
+    while stream.peek() != Some(")") {
+        match stream.next() {
+            Some(tok) if tok == "," => {}
+            Some(tok) => files.push(tok.trim_matches('"').to_string()),
+            None => return Err(eof_error(stream, "unterminated INPUT/GROUP")),
+        }
+    }
+    stream.expect(")")?;
+    stream.consume_optional(";");
+    Ok(files)
+}
+
+fn parse_memory(stream: &mut TokenStream, script: &mut LinkerScript) -> Result<(), SagError> {
+    stream.expect("{")?;
+    while stream.peek() != Some("}") {
+        let name = stream.next().ok_or_else(|| eof_error(stream, "unexpected end of file in MEMORY block"))?;
+        stream.expect("(")?;
+        let mut attrs = String::new();
+        while stream.peek() != Some(")") {
+            match stream.next() {
+                Some(tok) => attrs.push_str(&tok),
+                None => return Err(eof_error(stream, "unterminated region attributes")),
+            }
+        }
+        stream.expect(")")?;
+        stream.expect(":")?;
+        stream.expect("ORIGIN")?;
+        stream.expect("=")?;
+        let origin = parse_expr(stream)?;
+        stream.expect(",")?;
+        stream.expect("LENGTH")?;
+        stream.expect("=")?;
+        let length = parse_expr(stream)?;
+        script.memory_regions.insert(name, MemoryRegion { origin, length, attributes: attrs });
+    }
+    stream.expect("}")?;
+    Ok(())
+}
+
+fn parse_region_alias(stream: &mut TokenStream, script: &mut LinkerScript) -> Result<(), SagError> {
+    stream.expect("(")?;
+    let alias = stream
+        .next()
+        .ok_or_else(|| eof_error(stream, "unterminated REGION_ALIAS"))?
+        .trim_matches('"')
+        .to_string();
+    stream.expect(",")?;
+    let region = stream.next().ok_or_else(|| eof_error(stream, "unterminated REGION_ALIAS"))?;
+    stream.expect(")")?;
+    stream.consume_optional(";");
+    script.region_aliases.insert(alias, region);
+    Ok(())
+}
+
+fn parse_symbol_value(stream: &mut TokenStream) -> Result<LinkerScriptSymbolValue, SagError> {
+    match stream.peek() {
+        Some(".") => {
+            stream.next();
+            Ok(LinkerScriptSymbolValue::Location)
+        }
+        Some("LOADADDR") => {
+            stream.next();
+            stream.expect("(")?;
+            let section = stream.next().ok_or_else(|| eof_error(stream, "unterminated LOADADDR"))?;
+            stream.expect(")")?;
+            Ok(LinkerScriptSymbolValue::LoadAddrOf(section))
+        }
+        Some("ADDR") => {
+            // Also a location-counter-like expression -- no constant
+            // value is available without actually linking.
+            stream.next();
+            skip_balanced_parens(stream)?;
+            Ok(LinkerScriptSymbolValue::Location)
+        }
+        _ => Ok(LinkerScriptSymbolValue::Constant(parse_expr(stream)?)),
+    }
+}
+
+fn parse_symbol_or_skip(
+    stream: &mut TokenStream,
+    symbols: &mut Vec<(String, LinkerScriptSymbolValue)>,
+) -> Result<(), SagError> {
+    let name = stream.next().ok_or_else(|| eof_error(stream, "unexpected end of file"))?;
+    // `. = expr;` advances the location counter rather than naming a
+    // symbol; tolerate it like any other unmodeled directive instead of
+    // trying to capture a value for it.
+    if name != "." && stream.peek() == Some("=") {
+        stream.next();
+        if let Ok(value) = parse_symbol_value(stream) {
+            stream.consume_optional(";");
+            symbols.push((name, value));
+            return Ok(());
+        }
+    }
+    skip_directive(stream)?;
+    Ok(())
+}
+
+fn parse_provide(stream: &mut TokenStream, script: &mut LinkerScript) -> Result<(), SagError> {
+    stream.expect("(")?;
+    let name = stream.next().ok_or_else(|| eof_error(stream, "unterminated PROVIDE"))?;
+    stream.expect("=")?;
+    let value = parse_symbol_value(stream)?;
+    stream.expect(")")?;
+    stream.consume_optional(";");
+    script.symbols.push((name, value));
+    Ok(())
+}
+
+/// `*(.section)` (bare) or `KEEP(*(.section))`; returns the section
+/// name with its leading `.` and trailing wildcard `*` stripped.
+fn parse_wildcard_inner(stream: &mut TokenStream) -> Result<Option<String>, SagError> {
+    stream.expect("(")?;
+    let mut name = None;
+    while stream.peek() != Some(")") {
+        match stream.next() {
+            Some(tok) if tok.starts_with('.') => {
+                name = Some(tok.trim_start_matches('.').trim_end_matches('*').to_string());
+            }
+            Some(_) => {}
+            None => return Err(eof_error(stream, "unterminated input-section pattern")),
+        }
+    }
+    stream.expect(")")?;
+    Ok(name)
+}
+
+fn parse_keep_pattern(stream: &mut TokenStream) -> Result<Option<String>, SagError> {
+    stream.expect("(")?;
+    let pattern = if stream.peek() == Some("*") {
+        stream.next();
+        parse_wildcard_inner(stream)?
+    } else {
+        skip_balanced_parens(stream)?;
+        None
+    };
+    stream.expect(")")?;
+    Ok(pattern)
+}
+
+fn parse_section_body(stream: &mut TokenStream) -> Result<Vec<(String, bool)>, SagError> {
+    let mut inputs = Vec::new();
+    let mut depth = 1;
+    while depth > 0 {
+        match stream.peek() {
+            None => return Err(eof_error(stream, "unterminated output section body")),
+            Some("{") => {
+                stream.next();
+                depth += 1;
+            }
+            Some("}") => {
+                stream.next();
+                depth -= 1;
+            }
+            Some("KEEP") => {
+                stream.next();
+                if let Some(pattern) = parse_keep_pattern(stream)? {
+                    inputs.push((pattern, true));
+                }
+            }
+            Some("*") => {
+                stream.next();
+                if stream.peek() == Some("(") {
+                    if let Some(pattern) = parse_wildcard_inner(stream)? {
+                        inputs.push((pattern, false));
+                    }
+                }
+            }
+            // Tolerate anything else inside the body (symbol
+            // assignments, ALIGN(...), etc.) without modeling it.
+            Some(_) => {
+                stream.next();
+            }
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    inputs.retain(|pair| seen.insert(pair.clone()));
+    Ok(inputs)
+}
+
+/// Whether the name at the current token starts an output-section entry
+/// (`NAME [addr-expr] [(TYPE)] : ...`) as opposed to a symbol assignment
+/// or other directive at `SECTIONS` scope: scan ahead for a `:` before
+/// hitting `{`, `;`, or `}`.
+fn looks_like_output_section(stream: &TokenStream) -> bool {
+    let mut i = stream.pos + 1;
+    while let Some((tok, _)) = stream.tokens.get(i) {
+        match tok.as_str() {
+            ":" => return true,
+            "{" | ";" | "}" => return false,
+            _ => i += 1,
+        }
+    }
+    false
+}
+
+fn parse_output_section(stream: &mut TokenStream, script: &mut LinkerScript, name: String) -> Result<(), SagError> {
+    // Skip any address expression / `(TYPE)` before the `:`.
+    while stream.peek() != Some(":") {
+        if stream.next().is_none() {
+            return Err(eof_error(stream, "expected ':' in output section"));
+        }
+    }
+    stream.expect(":")?;
+
+    // `AT(expr)` sets the LMA -- parsed and discarded, see the struct doc.
+    if stream.peek() == Some("AT") {
+        stream.next();
+        skip_balanced_parens(stream)?;
+    }
+
+    stream.expect("{")?;
+    let inputs = parse_section_body(stream)?;
+
+    // `> REGION`
+    if stream.peek() == Some(">") {
+        stream.next();
+        stream.next();
+    }
+    // `AT> REGION2`
+    if stream.peek() == Some("AT") {
+        stream.next();
+        stream.expect(">")?;
+        stream.next();
+    }
+    // `=FILL`
+    if stream.peek() == Some("=") {
+        stream.next();
+        stream.next();
+    }
+
+    script.sections.push(LinkerScriptSection { name, inputs });
+    Ok(())
+}
+
+fn parse_sections(stream: &mut TokenStream, script: &mut LinkerScript) -> Result<(), SagError> {
+    stream.expect("{")?;
+    while stream.peek() != Some("}") {
+        let Some(name) = stream.peek().map(str::to_string) else {
+            break;
+        };
+        if name == "PROVIDE" {
+            stream.next();
+            parse_provide(stream, script)?;
+        } else if looks_like_output_section(stream) {
+            stream.next();
+            parse_output_section(stream, script, name)?;
+        } else {
+            parse_symbol_or_skip(stream, &mut script.symbols)?;
+        }
+    }
+    stream.expect("}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_address() {
+        assert!(matches!(Address::parse("0x80000000").unwrap(), Address::Absolute(0x80000000)));
+        assert!(matches!(Address::parse("+0").unwrap(), Address::Relative(0)));
+        assert!(matches!(Address::parse("+256").unwrap(), Address::Relative(256)));
+    }
+
+    #[test]
+    fn test_parse_simple_sag() {
+        let content = r#"
+USER_SECTIONS .bootloader
+
+HEAD 0x00000000
+{
+    BOOTLOADER 0x80000000
+    {
+        ADDR __flash_start
+        * KEEP ( .bootloader )
+    }
+}
+"#;
+        let sag = SagFile::parse(content).unwrap();
+        assert_eq!(sag.user_sections.len(), 1);
+        assert_eq!(sag.blocks.len(), 1);
+        assert_eq!(sag.blocks[0].regions.len(), 1);
+    }
+
+    #[test]
+    fn test_recovering_names_most_recent_unmatched_brace_at_eof() {
+        // BOOTLOADER's region is never closed and the file just ends;
+        // the error should blame BOOTLOADER's '{', not HEAD's.
+        let content = r#"
+HEAD 0x00000000
+{
+    BOOTLOADER 0x80000000
+    {
+        ADDR __flash_start
+"#;
+        let (sag, errors) = SagFile::parse_recovering(content);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("unclosed"));
+        assert!(errors[0].to_string().contains("line 5"));
+        assert_eq!(sag.unwrap().blocks.len(), 0);
+    }
+
+    #[test]
+    fn test_recovering_skips_malformed_block_and_resumes_at_next_keyword() {
+        // BOOTLOADER's region header is missing its opening brace, which
+        // used to abort parsing entirely; recovery should skip ahead to
+        // the next column-0 block keyword and still pick up EXEC.
+        let content = r#"
+HEAD 0x00000000
+{
+    BOOTLOADER 0x80000000
+    ADDR __flash_start
+}
+
+EXEC +0
+{
+    TEXT +0
+    {
+        * ( .text )
+    }
+}
+"#;
+        let (sag, errors) = SagFile::parse_recovering(content);
+        assert_eq!(errors.len(), 1);
+
+        let sag = sag.unwrap();
+        assert_eq!(sag.blocks.len(), 1);
+        assert_eq!(sag.blocks[0].block_type, "EXEC");
+    }
+
+    #[test]
+    fn test_recovering_reports_stray_closing_brace() {
+        let content = r#"
+}
+
+EXEC +0
+{
+    TEXT +0
+    {
+        * ( .text )
+    }
+}
+"#;
+        let (sag, errors) = SagFile::parse_recovering(content);
+        assert_eq!(errors.len(), 1);
+        let sag = sag.unwrap();
+        assert_eq!(sag.blocks.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_accepts_well_placed_layout() {
+        let content = r#"
+HEAD 0x80000000
+{
+    CODE 0x80000000
+    {
+        * ( +RO )
+    }
+}
+"#;
+        let sag = SagFile::parse(content).unwrap();
+        assert!(sag.validate(&LinkerScriptConfig::ae350_ddr()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_detects_overlap_oob_and_writable_into_rx() {
+        let content = r#"
+HEAD 0x80000000
+{
+    CODE 0x80000000
+    {
+        * ( +RO )
+    }
+}
+
+EXEC 0x00000000
+{
+    DATA1 0x00000000
+    {
+        * ( +RW )
+    }
+    DATA2 0x00000000
+    {
+        * ( +RW )
+    }
+}
+
+MEM 0x80001000
+{
+    BADDATA 0x80001000
+    {
+        * ( +RW )
+    }
+}
+
+LDSECTION 0xF0000000
+{
+    OOB 0xF0000000
+    {
+        * ( +ZI )
+    }
+}
+"#;
+        let sag = SagFile::parse(content).unwrap();
+        let errors = sag.validate(&LinkerScriptConfig::ae350_ddr()).unwrap_err();
+
+        assert!(errors.iter().any(|e| matches!(e, LayoutError::Overlap { .. })));
+        assert!(errors.iter().any(|e| matches!(e, LayoutError::WritableIntoReadOnly { .. })));
+        assert!(errors.iter().any(|e| matches!(e, LayoutError::VmaOutOfBounds { .. })));
+        assert!(errors.iter().any(|e| matches!(e, LayoutError::LmaOutOfBounds { .. })));
+    }
+
+    #[test]
+    fn test_expand_section_pattern_uses_config_groups_with_literal_fallback() {
+        let mut config = LinkerScriptConfig::ae350_ddr();
+        config
+            .section_groups
+            .insert("+FAST".to_string(), vec!["itcm_text".to_string()]);
+
+        let sag = SagFile {
+            user_sections: Vec::new(),
+            blocks: Vec::new(),
+        };
+
+        assert_eq!(sag.expand_section_pattern("+FAST", &config), vec!["itcm_text"]);
+        assert_eq!(sag.expand_section_pattern("+RO", &config), vec!["text", "rodata", "srodata"]);
+        assert_eq!(sag.expand_section_pattern(".custom_section", &config), vec!["custom_section"]);
+        assert_eq!(sag.expand_section_pattern("+UNKNOWN", &config), vec!["+UNKNOWN"]);
+    }
+
+    #[test]
+    fn test_force_active_and_fill_value_are_noops_when_unset() {
+        let content = r#"
+HEAD 0x80000000
+{
+    CODE 0x80000000
+    {
+        * ( +RO )
+    }
+}
+"#;
+        let sag = SagFile::parse(content).unwrap();
+        let script = sag.to_linker_script(&LinkerScriptConfig::ae350_ddr());
+        assert!(!script.contains(".force_active"));
+        assert!(!script.contains(" =0x"));
+    }
+
+    #[test]
+    fn test_force_active_keeps_sections_and_symbols() {
+        let content = r#"
+HEAD 0x80000000
+{
+    CODE 0x80000000
+    {
+        * ( +RO )
+    }
+}
+"#;
+        let sag = SagFile::parse(content).unwrap();
+        let mut config = LinkerScriptConfig::ae350_ddr();
+        config.force_active = vec![".vector_table".to_string(), "Reset_Handler".to_string()];
+        config.fill_value = Some(0xFFFF_FFFF);
+
+        let script = sag.to_linker_script(&config);
+        assert!(script.contains(".force_active"));
+        assert!(script.contains("KEEP(*(.vector_table))"));
+        assert!(script.contains("PROVIDE(Reset_Handler = .);"));
+        assert!(script.contains("ASSERT(DEFINED(Reset_Handler)"));
+        assert!(script.contains("} > FLASH =0xFFFFFFFF"));
+    }
+
+    #[test]
+    fn test_xip_config_emits_symbolic_at_and_data_relocation_symbols() {
+        let content = r#"
+HEAD 0x80000000
+{
+    DATA 0x00000000
+    {
+        * ( .data )
+    }
+}
+"#;
+        let sag = SagFile::parse(content).unwrap();
+        let config = LinkerScriptConfig::ae350_xip();
+
+        let script = sag.to_linker_script(&config);
+        // `AT> REGION` is only valid after the closing brace; GNU ld
+        // rejects it in the pre-brace position this section used to emit.
+        assert!(!script.contains("AT> FLASH\n    {"));
+        assert!(script.contains(".data :\n"));
+        assert!(script.contains("_sdata = .;"));
+        assert!(script.contains("_edata = .;"));
+        assert!(script.contains("_sidata = LOADADDR(.data);"));
+        assert!(script.contains("} > DDR AT> FLASH"));
+    }
+
+    #[test]
+    fn test_riscv_rt_flavor_emits_region_aliases_and_skips_preamble() {
+        let content = r#"
+HEAD 0x80000000
+{
+    BOOTLOADER 0x80000000
+    {
+        STACK = 0x80100000
+        * ( +RO )
+    }
+}
+"#;
+        let sag = SagFile::parse(content).unwrap();
+        let mut config = LinkerScriptConfig::ae350_ddr();
+        config.flavor = LinkerFlavor::RiscvRt;
+
+        let script = sag.to_linker_script(&config);
+        assert!(!script.contains("OUTPUT_ARCH"));
+        assert!(!script.contains("ENTRY(_start)"));
+        assert!(script.contains("REGION_ALIAS(\"REGION_TEXT\", FLASH);"));
+        assert!(script.contains("REGION_ALIAS(\"REGION_DATA\", DDR);"));
+        assert!(script.contains("REGION_ALIAS(\"REGION_STACK\", DDR);"));
+        assert!(script.contains("__stack_top = 0x80100000;"));
+        assert!(script.contains("_stack_start = 0x80100000;"));
+    }
+
+    #[test]
+    fn test_bare_metal_flavor_renames_stack_symbol_and_bounds_bss() {
+        let content = r#"
+HEAD 0x80000000
+{
+    BOOTLOADER 0x80000000
+    {
+        STACK = 0x80100000
+        * ( .bss )
+    }
+}
+"#;
+        let sag = SagFile::parse(content).unwrap();
+        let mut config = LinkerScriptConfig::ae350_ddr();
+        config.flavor = LinkerFlavor::BareMetal;
+
+        let script = sag.to_linker_script(&config);
+        assert!(script.contains("__stack = 0x80100000;"));
+        assert!(!script.contains("__stack_top"));
+        assert!(script.contains("_sbss = .;"));
+        assert!(script.contains("_ebss = .;"));
+    }
+
+    #[test]
+    fn test_to_map_report_lists_regions_and_symbols() {
+        let content = r#"
+HEAD 0x80000000
+{
+    BOOTLOADER 0x80000000
+    {
+        ADDR __flash_start
+        STACK = 0x80100000
+        * ( +RO )
+    }
+}
+"#;
+        let sag = SagFile::parse(content).unwrap();
+        let report = sag.to_map_report(&LinkerScriptConfig::ae350_ddr());
+
+        assert!(report.contains("BOOTLOADER"));
+        assert!(report.contains("FLASH"));
+        assert!(report.contains("__flash_start"));
+        assert!(report.contains("__stack_top"));
+        assert!(report.contains("0x80100000"));
+    }
+
+    #[test]
+    fn test_with_stack_guard_shrinks_region_to_top_and_records_boundary() {
+        let config = LinkerScriptConfig::ae350_ddr();
+        let ram = config.memory_regions.get("DDR").unwrap().clone();
+
+        let guarded = config.with_stack_guard("DDR", 0x1000).unwrap();
+
+        let region = guarded.memory_regions.get("DDR").unwrap();
+        assert_eq!(region.length, 0x1000);
+        assert_eq!(region.origin, ram.origin + ram.length - 0x1000);
+        assert_eq!(guarded.stack_guard_boundary, Some(region.origin));
+
+        let content = r#"
+HEAD 0x80000000
+{
+    BOOTLOADER 0x80000000
+    {
+        * ( +RO )
+    }
+}
+"#;
+        let script = SagFile::parse(content).unwrap().to_linker_script(&guarded);
+        assert!(script.contains(&format!("_stack_start = {:#010X};", region.origin)));
+    }
+
+    #[test]
+    fn test_with_stack_guard_errors_instead_of_panicking_when_static_size_exceeds_region() {
+        let config = LinkerScriptConfig::ae350_ddr();
+        let ram = config.memory_regions.get("DDR").unwrap().clone();
+
+        let err = config.with_stack_guard("DDR", ram.length + 1).unwrap_err();
+        assert!(matches!(err, SagError::InvalidAddress(_)));
+    }
+
+    /// Hand-builds a minimal 64-bit LE ELF with one section header per
+    /// `(addr, size, alloc)` entry so `measure_region_usage` has something to sum, without
+    /// requiring a real toolchain/object in this sandbox.
+    fn synthetic_elf(sections: &[(u64, u64, bool)]) -> Vec<u8> {
+        const SHF_ALLOC: u64 = 0x2;
+        let mut bytes = vec![0u8; 64];
+        bytes[0..4].copy_from_slice(&[0x7f, b'E', b'L', b'F']);
+        bytes[4] = 2; // ELFCLASS64
+        bytes[5] = 1; // ELFDATA2LSB
+
+        let e_shentsize: u64 = 64;
+        let e_shoff = bytes.len() as u64;
+        bytes[40..48].copy_from_slice(&e_shoff.to_le_bytes());
+        bytes[58..60].copy_from_slice(&(e_shentsize as u16).to_le_bytes());
+        bytes[60..62].copy_from_slice(&(sections.len() as u16).to_le_bytes());
+
+        for (addr, size, alloc) in sections {
+            let mut shdr = vec![0u8; e_shentsize as usize];
+            let flags = if *alloc { SHF_ALLOC } else { 0 };
+            shdr[8..16].copy_from_slice(&flags.to_le_bytes());
+            shdr[16..24].copy_from_slice(&addr.to_le_bytes());
+            shdr[32..40].copy_from_slice(&size.to_le_bytes());
+            bytes.extend_from_slice(&shdr);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_measure_region_usage_sums_alloc_sections_inside_region() {
+        let region = MemoryRegion {
+            origin: 0x8000_0000,
+            length: 0x1000,
+            attributes: "rwx".to_string(),
+        };
+        let elf = synthetic_elf(&[
+            (0x8000_0000, 0x100, true),  // inside, alloc -> counted
+            (0x8000_0200, 0x40, false),  // inside, not alloc -> skipped
+            (0x9000_0000, 0x10, true),   // outside region -> skipped
+        ]);
+
+        let usage = measure_region_usage(&elf, &region).unwrap();
+        assert_eq!(usage, 0x100);
+    }
+
+    #[test]
+    fn test_measure_region_usage_rejects_non_elf() {
+        let region = MemoryRegion {
+            origin: 0,
+            length: 0x1000,
+            attributes: "rwx".to_string(),
+        };
+        let err = measure_region_usage(b"not an elf file padding......", &region).unwrap_err();
+        assert!(matches!(err, SagError::Elf(_)));
+    }
+
+    #[test]
+    fn test_measure_region_usage_rejects_overflowing_section_header_offset() {
+        let region = MemoryRegion {
+            origin: 0,
+            length: 0x1000,
+            attributes: "rwx".to_string(),
+        };
+        let mut elf = vec![0u8; 64];
+        elf[0..4].copy_from_slice(&[0x7f, b'E', b'L', b'F']);
+        elf[4] = 2;
+        elf[5] = 1;
+        // A crafted e_shoff near u64::MAX, combined with a nonzero
+        // e_shnum/e_shentsize, would overflow `e_shoff + i * e_shentsize`
+        // unchecked; it must error instead of panicking.
+        elf[40..48].copy_from_slice(&u64::MAX.to_le_bytes());
+        elf[58..60].copy_from_slice(&64u16.to_le_bytes());
+        elf[60..62].copy_from_slice(&1u16.to_le_bytes());
+
+        let err = measure_region_usage(&elf, &region).unwrap_err();
+        assert!(matches!(err, SagError::Elf(_)));
+    }
+
+    #[test]
+    fn test_linker_script_parses_memory_region_alias_and_symbols() {
+        let content = r#"
+/* generated */
+OUTPUT_ARCH(riscv)
+ENTRY(_start)
+
+MEMORY
+{
+    FLASH (rx)  : ORIGIN = 0x80000000, LENGTH = 256M
+    DDR (rwx)   : ORIGIN = 0x00000000, LENGTH = 128M
+}
+
+REGION_ALIAS("REGION_TEXT", FLASH);
+
+__stack_top = 0x80100000;
+"#;
+        let script = LinkerScript::parse(content).unwrap();
+
+        assert_eq!(script.output_arch.as_deref(), Some("riscv"));
+        assert_eq!(script.entry.as_deref(), Some("_start"));
+
+        let flash = script.memory_regions.get("FLASH").unwrap();
+        assert_eq!(flash.origin, 0x8000_0000);
+        assert_eq!(flash.length, 256 * 1024 * 1024);
+
+        assert_eq!(script.region_aliases.get("REGION_TEXT").map(String::as_str), Some("FLASH"));
+        assert_eq!(
+            script.symbols,
+            vec![("__stack_top".to_string(), LinkerScriptSymbolValue::Constant(0x8010_0000))]
+        );
+    }
+
+    #[test]
+    fn test_linker_script_round_trips_a_sag2ld_generated_section() {
+        let content = r#"
+SECTIONS
+{
+    .text : AT(0x80000000)
+    {
+        KEEP(*(.text))
+        KEEP(*(.text*))
+    } > FLASH
+
+    _sidata = LOADADDR(.data);
+}
+"#;
+        let script = LinkerScript::parse(content).unwrap();
+
+        assert_eq!(script.sections.len(), 1);
+        assert_eq!(script.sections[0].name, ".text");
+        assert_eq!(script.sections[0].inputs, vec![("text".to_string(), true)]);
+        assert_eq!(
+            script.symbols,
+            vec![("_sidata".to_string(), LinkerScriptSymbolValue::LoadAddrOf(".data".to_string()))]
+        );
+
+        let sag = script.to_sag_file();
+        assert_eq!(sag.blocks.len(), 1);
+        let names: Vec<&str> = sag.blocks[0].regions.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["text", "_sidata"]);
+        assert!(matches!(
+            sag.blocks[0].regions[0].directives[0],
+            Directive::Section { keep: true, .. }
+        ));
+        assert!(matches!(sag.blocks[0].regions[1].directives[0], Directive::LoadAddr { .. }));
+    }
+
+    #[test]
+    fn test_linker_script_preserves_non_stack_constant_symbol_names() {
+        let content = r#"
+SECTIONS
+{
+    _heap_size = 0x2000;
+    __custom_marker = 0x12345678;
+    __stack_top = 0x80100000;
+}
+"#;
+        let sag = LinkerScript::parse(content).unwrap().to_sag_file();
+
+        let directive_for = |name: &str| -> &Directive {
+            let region = sag.blocks[0].regions.iter().find(|r| r.name == name).unwrap();
+            &region.directives[0]
+        };
+
+        assert!(matches!(
+            directive_for("_heap_size"),
+            Directive::Constant { symbol, value } if symbol == "_heap_size" && *value == 0x2000
+        ));
+        assert!(matches!(
+            directive_for("__custom_marker"),
+            Directive::Constant { symbol, value } if symbol == "__custom_marker" && *value == 0x1234_5678
+        ));
+        assert!(matches!(directive_for("__stack_top"), Directive::Stack(0x8010_0000)));
+
+        let script = sag.to_linker_script(&LinkerScriptConfig::ae350_ddr());
+        assert!(script.contains("_heap_size = 0x00002000;"));
+        assert!(script.contains("__custom_marker = 0x12345678;"));
+    }
+
+    #[test]
+    fn test_linker_script_tolerates_unknown_directives() {
+        let content = r#"
+ASSERT(ORIGIN(FLASH) == 0x80000000, "bad origin");
+
+SECTIONS
+{
+    . = ALIGN(4);
+    .text : { *(.text) } > FLASH
+}
+"#;
+        let script = LinkerScript::parse(content).unwrap();
+        assert_eq!(script.sections.len(), 1);
+        assert_eq!(script.sections[0].inputs, vec![("text".to_string(), false)]);
     }
 }
@@ -2,29 +2,166 @@
 //!
 //! Usage:
 //!   sag2ld input.sag -o output.ld [--config ddr|ilm|xip]
+//!
+//! Can also be dropped in as `rustflags = ["-C", "linker=sag2ld"]`: set
+//! `SAG2LD_SAG` to the path of the SAG file and sag2ld transparently
+//! wraps the real linker instead, generating `memory.x` on the fly. See
+//! [`run_as_linker_wrapper`].
 
-use sag_parser::{LinkerScriptConfig, SagFile};
+use sag_parser::{LinkerFlavor, LinkerScriptConfig, SagFile};
 use std::env;
 use std::fs;
 use std::process;
 
+/// Linker invoked by [`run_as_linker_wrapper`] when `SAG2LD_LINKER` isn't
+/// set. rustc's RISC-V targets normally drive the link through the C
+/// compiler frontend rather than calling `ld` directly.
+const DEFAULT_LINKER: &str = "cc";
+
+/// Find the path following `-o` in a forwarded linker argv.
+fn get_output_path(args: &[String]) -> Option<&str> {
+    args.iter()
+        .position(|a| a == "-o")
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Parse a `--flavor`/`SAG2LD_FLAVOR` value into a [`LinkerFlavor`].
+fn parse_flavor(name: &str) -> Option<LinkerFlavor> {
+    match name {
+        "gnu-ld" => Some(LinkerFlavor::GnuLd),
+        "lld" => Some(LinkerFlavor::Lld),
+        "riscv-rt" => Some(LinkerFlavor::RiscvRt),
+        "bare-metal" => Some(LinkerFlavor::BareMetal),
+        _ => None,
+    }
+}
+
+/// Transparent linker-wrapper mode: treat `args` as the real linker's
+/// argv (as forwarded by rustc when `linker=sag2ld`), generate
+/// `memory.x` from the SAG file at `sag_path` into a temp dir, append
+/// `-L <tempdir>` and `-T <script>`, then exec the real linker and
+/// propagate its exit code.
+///
+/// Composes with the two-pass stack-guard flow: if `SAG2LD_STACK_GUARD`
+/// names a region and the linker's output path already exists (from a
+/// prior, unguarded link), that ELF is measured and the region is
+/// shrunk against the stack before this pass links.
+fn run_as_linker_wrapper(sag_path: &str, args: Vec<String>) -> ! {
+    let output_path = get_output_path(&args);
+
+    let sag = match SagFile::from_file(sag_path) {
+        Ok(sag) => sag,
+        Err(e) => {
+            eprintln!("sag2ld (linker wrapper): failed to parse {}: {}", sag_path, e);
+            process::exit(1);
+        }
+    };
+
+    let config_name = env::var("SAG2LD_CONFIG").unwrap_or_else(|_| "ddr".to_string());
+    let mut config = match config_name.as_str() {
+        "ddr" => LinkerScriptConfig::ae350_ddr(),
+        "ilm" => LinkerScriptConfig::ae350_ilm(),
+        "xip" => LinkerScriptConfig::ae350_xip(),
+        other => {
+            eprintln!("sag2ld (linker wrapper): unknown SAG2LD_CONFIG '{}'", other);
+            process::exit(1);
+        }
+    };
+
+    if let Ok(flavor_name) = env::var("SAG2LD_FLAVOR") {
+        match parse_flavor(&flavor_name) {
+            Some(flavor) => config.flavor = flavor,
+            None => {
+                eprintln!("sag2ld (linker wrapper): unknown SAG2LD_FLAVOR '{}'", flavor_name);
+                process::exit(1);
+            }
+        }
+    }
+
+    if let Ok(region_name) = env::var("SAG2LD_STACK_GUARD") {
+        match (output_path, config.memory_regions.get(&region_name).cloned()) {
+            (Some(out_path), Some(region)) => {
+                if let Ok(elf_bytes) = fs::read(out_path) {
+                    match sag_parser::measure_region_usage(&elf_bytes, &region) {
+                        Ok(usage) => match config.with_stack_guard(&region_name, usage) {
+                            Ok(guarded) => {
+                                config = guarded;
+                                eprintln!(
+                                    "sag2ld (linker wrapper): {} uses {} bytes of {}; guarding the stack",
+                                    out_path, usage, region_name
+                                );
+                            }
+                            Err(e) => eprintln!("sag2ld (linker wrapper): couldn't guard {}: {}", region_name, e),
+                        },
+                        Err(e) => eprintln!("sag2ld (linker wrapper): couldn't measure {}: {}", out_path, e),
+                    }
+                }
+            }
+            (_, None) => eprintln!(
+                "sag2ld (linker wrapper): unknown memory region '{}' for SAG2LD_STACK_GUARD",
+                region_name
+            ),
+            (None, _) => {}
+        }
+    }
+
+    let linker_script = sag.to_linker_script(&config);
+
+    let script_dir = env::temp_dir().join(format!("sag2ld-{}", process::id()));
+    fs::create_dir_all(&script_dir).expect("Failed to create temp dir for linker script");
+    let script_path = script_dir.join("memory.x");
+    fs::write(&script_path, &linker_script).expect("Failed to write linker script");
+
+    let mut real_args = args;
+    real_args.push("-L".to_string());
+    real_args.push(script_dir.display().to_string());
+    real_args.push("-T".to_string());
+    real_args.push(script_path.display().to_string());
+
+    let linker = env::var("SAG2LD_LINKER").unwrap_or_else(|_| DEFAULT_LINKER.to_string());
+    let status = process::Command::new(&linker).args(&real_args).status().unwrap_or_else(|e| {
+        eprintln!("sag2ld (linker wrapper): failed to exec '{}': {}", linker, e);
+        process::exit(1);
+    });
+
+    process::exit(status.code().unwrap_or(1));
+}
+
 fn print_usage() {
     eprintln!(
         r#"sag2ld - Convert Andes SAG files to GNU LD linker scripts
 
 USAGE:
-    sag2ld <input.sag> [OPTIONS]
+    sag2ld <input.sag|input.x|input.ld> [OPTIONS]
+
+    A `.x`/`.ld` input is parsed as a GNU LD linker script and imported
+    into the same internal representation a `.sag` file produces, so
+    --print-ast works on either format and a vendor-provided script can
+    be diffed against a SAG-generated one.
 
 OPTIONS:
     -o, --output <file>     Output linker script path (default: stdout)
-    -c, --config <name>     Memory config preset: ddr, ilm (default: ddr)
+    -c, --config <name>     Memory config preset: ddr, ilm, xip (default: ddr)
+    -f, --flavor <name>     Output flavor: gnu-ld, lld, riscv-rt, bare-metal
+                            (default: gnu-ld). Controls REGION_ALIAS names,
+                            the stack symbol, and whether the OUTPUT_ARCH/
+                            ENTRY preamble is forced -- see LinkerFlavor.
     -p, --print-ast         Print parsed AST instead of linker script
+    --stack-guard <REGION>  Two-pass flip-link-style build: link once with
+                            <file.elf next to -o>, measure how much of
+                            <REGION> is in use, then regenerate the linker
+                            script with the stack pinned against unmapped
+                            memory. Requires -o.
     -h, --help              Show this help message
 
 EXAMPLES:
     sag2ld ae350-ddr.sag -o memory.x
     sag2ld ae350-ilm.sag --config ilm -o memory.x
     sag2ld ae350-ddr.sag --print-ast
+    sag2ld ae350-ddr.sag -o memory.x --stack-guard DDR
+    sag2ld ae350-xip.sag --config xip -o memory.x
+    sag2ld ae350-ddr.sag --flavor riscv-rt -o memory.x
 "#
     );
 }
@@ -32,6 +169,10 @@ EXAMPLES:
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+    if let Ok(sag_path) = env::var("SAG2LD_SAG") {
+        run_as_linker_wrapper(&sag_path, args.into_iter().skip(1).collect());
+    }
+
     if args.len() < 2 {
         print_usage();
         process::exit(1);
@@ -40,7 +181,9 @@ fn main() {
     let mut input_path: Option<&str> = None;
     let mut output_path: Option<&str> = None;
     let mut config_name = "ddr";
+    let mut flavor_name = "gnu-ld";
     let mut print_ast = false;
+    let mut stack_guard_region: Option<&str> = None;
 
     let mut i = 1;
     while i < args.len() {
@@ -60,14 +203,30 @@ fn main() {
             "-c" | "--config" => {
                 i += 1;
                 if i >= args.len() {
-                    eprintln!("Error: --config requires a name (ddr, ilm)");
+                    eprintln!("Error: --config requires a name (ddr, ilm, xip)");
                     process::exit(1);
                 }
                 config_name = &args[i];
             }
+            "-f" | "--flavor" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --flavor requires a name (gnu-ld, lld, riscv-rt, bare-metal)");
+                    process::exit(1);
+                }
+                flavor_name = &args[i];
+            }
             "-p" | "--print-ast" => {
                 print_ast = true;
             }
+            "--stack-guard" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --stack-guard requires a region name");
+                    process::exit(1);
+                }
+                stack_guard_region = Some(&args[i]);
+            }
             arg if arg.starts_with('-') => {
                 eprintln!("Error: Unknown option: {}", arg);
                 print_usage();
@@ -89,12 +248,35 @@ fn main() {
         }
     };
 
-    // Parse the SAG file
-    let sag = match SagFile::from_file(input_path) {
-        Ok(sag) => sag,
-        Err(e) => {
-            eprintln!("Error parsing {}: {}", input_path, e);
-            process::exit(1);
+    // Parse the input. A `.x`/`.ld` extension is treated as a GNU LD
+    // linker script (hand-written, or a previous sag2ld output to
+    // re-ingest) and converted into the same AST a `.sag` file produces,
+    // so both `--print-ast` output and the generation pipeline below
+    // work the same either way -- letting a SAG-generated script be
+    // diffed against a vendor-provided one.
+    let is_linker_script = input_path.ends_with(".x") || input_path.ends_with(".ld");
+    let sag = if is_linker_script {
+        let content = match fs::read_to_string(input_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", input_path, e);
+                process::exit(1);
+            }
+        };
+        match sag_parser::LinkerScript::parse(&content) {
+            Ok(script) => script.to_sag_file(),
+            Err(e) => {
+                eprintln!("Error parsing {}: {}", input_path, e);
+                process::exit(1);
+            }
+        }
+    } else {
+        match SagFile::from_file(input_path) {
+            Ok(sag) => sag,
+            Err(e) => {
+                eprintln!("Error parsing {}: {}", input_path, e);
+                process::exit(1);
+            }
         }
     };
 
@@ -104,17 +286,78 @@ fn main() {
     }
 
     // Select config
-    let config = match config_name {
+    let mut config = match config_name {
         "ddr" => LinkerScriptConfig::ae350_ddr(),
         "ilm" => LinkerScriptConfig::ae350_ilm(),
+        "xip" => LinkerScriptConfig::ae350_xip(),
         other => {
-            eprintln!("Error: Unknown config '{}'. Use 'ddr' or 'ilm'.", other);
+            eprintln!("Error: Unknown config '{}'. Use 'ddr', 'ilm', or 'xip'.", other);
             process::exit(1);
         }
     };
+    match parse_flavor(flavor_name) {
+        Some(flavor) => config.flavor = flavor,
+        None => {
+            eprintln!(
+                "Error: Unknown flavor '{}'. Use 'gnu-ld', 'lld', 'riscv-rt', or 'bare-metal'.",
+                flavor_name
+            );
+            process::exit(1);
+        }
+    }
 
     // Generate linker script
-    let linker_script = sag.to_linker_script(&config);
+    let mut linker_script = sag.to_linker_script(&config);
+
+    // Flip-link-style stack-overflow protection: the first pass above
+    // links with the region at its normal size. Once that link has
+    // produced an ELF (conventionally `<output>.elf`, built by the
+    // caller between two invocations of sag2ld with identical args), the
+    // second pass measures how much of the guarded region is actually
+    // used and re-emits the script with the stack pinned against
+    // unmapped memory instead of against whatever's above it.
+    if let Some(region_name) = stack_guard_region {
+        let Some(out_path) = output_path else {
+            eprintln!("Error: --stack-guard requires -o/--output, so there's a stable <output>.elf to look for");
+            process::exit(1);
+        };
+        let region = match config.memory_regions.get(region_name) {
+            Some(region) => region.clone(),
+            None => {
+                eprintln!("Error: unknown memory region '{}' for --stack-guard", region_name);
+                process::exit(1);
+            }
+        };
+        let elf_path = format!("{}.elf", out_path);
+        match fs::read(&elf_path) {
+            Ok(elf_bytes) => match sag_parser::measure_region_usage(&elf_bytes, &region) {
+                Ok(usage) => match config.with_stack_guard(region_name, usage) {
+                    Ok(guarded) => {
+                        linker_script = sag.to_linker_script(&guarded);
+                        eprintln!(
+                            "Stack-guard: {} uses {} bytes of {}; re-linking with the stack pinned below it",
+                            elf_path, usage, region_name
+                        );
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(1);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Error measuring {}: {}", elf_path, e);
+                    process::exit(1);
+                }
+            },
+            Err(_) => {
+                eprintln!(
+                    "Stack-guard: {} not found yet; writing an unguarded first-pass script. \
+                     Link, then re-run sag2ld with the same arguments to apply the guard.",
+                    elf_path
+                );
+            }
+        }
+    }
 
     // Output
     match output_path {